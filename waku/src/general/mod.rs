@@ -4,7 +4,9 @@
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 // crates
+use base64::Engine;
 use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 use sscanf::{scanf, RegexRepresentation};
 // internal
 
@@ -50,7 +52,31 @@ pub struct WakuMessage {
     timestamp: usize,
 }
 
+impl WakuMessage {
+    /// Build a new message out of its plaintext `payload`
+    pub fn new(
+        payload: impl Into<Box<[u8]>>,
+        content_topic: WakuContentTopic,
+        version: WakuMessageVersion,
+        timestamp: usize,
+    ) -> Self {
+        Self {
+            payload: payload.into(),
+            content_topic,
+            version,
+            timestamp,
+        }
+    }
+
+    /// The content topic to be set on the message
+    pub fn content_topic(&self) -> &WakuContentTopic {
+        &self.content_topic
+    }
+}
+
 /// A payload once decoded, used when a received Waku Message is encrypted
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DecodedPayload {
     /// Public key that signed the message (optional), hex encoded with 0x prefix
     public_key: Option<String>,
@@ -62,6 +88,35 @@ pub struct DecodedPayload {
     padding: String,
 }
 
+fn decode_hex_with_prefix(s: &str) -> Result<Vec<u8>> {
+    hex::decode(s.strip_prefix("0x").unwrap_or(s)).map_err(|err| err.to_string())
+}
+
+impl DecodedPayload {
+    /// The decrypted message payload, base64-decoded
+    pub fn data_bytes(&self) -> Result<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.data)
+            .map_err(|err| err.to_string())
+    }
+
+    /// The public key that signed the message, hex-decoded
+    pub fn public_key_bytes(&self) -> Result<Option<Vec<u8>>> {
+        self.public_key
+            .as_deref()
+            .map(decode_hex_with_prefix)
+            .transpose()
+    }
+
+    /// The message signature, hex-decoded
+    pub fn signature_bytes(&self) -> Result<Option<Vec<u8>>> {
+        self.signature
+            .as_deref()
+            .map(decode_hex_with_prefix)
+            .transpose()
+    }
+}
+
 /// The content topic of a Waku message
 /// as per the [specification](https://rfc.vac.dev/spec/36/#contentfilter-type)
 #[derive(Clone, Serialize, Deserialize)]
@@ -71,6 +126,13 @@ pub struct ContentFilter {
     content_topic: WakuContentTopic,
 }
 
+impl ContentFilter {
+    /// Build a new content filter
+    pub fn new(content_topic: WakuContentTopic) -> Self {
+        Self { content_topic }
+    }
+}
+
 /// The criteria to create subscription to a light node in JSON Format
 /// as per the [specification](https://rfc.vac.dev/spec/36/#filtersubscription-type)
 #[derive(Clone, Serialize, Deserialize)]
@@ -82,6 +144,16 @@ pub struct FilterSubscription {
     pubsub_topic: Option<WakuPubSubTopic>,
 }
 
+impl FilterSubscription {
+    /// Build a new filter subscription
+    pub fn new(content_filters: Vec<ContentFilter>, pubsub_topic: Option<WakuPubSubTopic>) -> Self {
+        Self {
+            content_filters,
+            pubsub_topic,
+        }
+    }
+}
+
 /// Criteria used to retrieve historical messages
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -110,6 +182,51 @@ pub struct StoreResponse {
     paging_options: Option<PagingOptions>,
 }
 
+impl StoreQuery {
+    /// Build a new store query
+    pub fn new(
+        pubsub_topic: Option<WakuPubSubTopic>,
+        content_filters: Vec<ContentFilter>,
+        start_time: Option<usize>,
+        end_time: Option<usize>,
+        paging_options: Option<PagingOptions>,
+    ) -> Self {
+        Self {
+            pubsub_topic,
+            content_filters,
+            start_time,
+            end_time,
+            paging_options,
+        }
+    }
+
+    /// Return a copy of this query with its [`PagingOptions`] cursor replaced,
+    /// used to resume a query from the cursor returned in a [`StoreResponse`]. If this query had
+    /// no [`PagingOptions`] set, default ones are created so the cursor actually takes effect
+    pub(crate) fn with_cursor(mut self, cursor: Option<MessageIndex>) -> Self {
+        self.paging_options = Some(
+            self.paging_options
+                .unwrap_or_else(PagingOptions::forward_with_default_page_size)
+                .with_cursor(cursor),
+        );
+        self
+    }
+}
+
+impl StoreResponse {
+    /// The historical messages retrieved by the query
+    pub fn messages(&self) -> &[WakuMessage] {
+        &self.messages
+    }
+
+    /// The cursor to resume the query from, if the result was paginated and there are further pages
+    pub(crate) fn cursor(&self) -> Option<&MessageIndex> {
+        self.paging_options
+            .as_ref()
+            .and_then(|paging| paging.cursor.as_ref())
+    }
+}
+
 /// Paging information
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -124,6 +241,31 @@ pub struct PagingOptions {
     forward: bool,
 }
 
+/// Default page size used when paging is needed but the caller did not specify one,
+/// e.g. when [`StoreQuery::with_cursor`] has to create [`PagingOptions`] from scratch
+const DEFAULT_PAGE_SIZE: usize = 100;
+
+impl PagingOptions {
+    /// Build new paging options
+    pub fn new(page_size: usize, cursor: Option<MessageIndex>, forward: bool) -> Self {
+        Self {
+            page_size,
+            cursor,
+            forward,
+        }
+    }
+
+    /// Paging options paging forward with [`DEFAULT_PAGE_SIZE`] and no cursor
+    fn forward_with_default_page_size() -> Self {
+        Self::new(DEFAULT_PAGE_SIZE, None, true)
+    }
+
+    fn with_cursor(mut self, cursor: Option<MessageIndex>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageIndex {
@@ -137,7 +279,24 @@ pub struct MessageIndex {
     pubsub_topic: WakuPubSubTopic,
 }
 
-#[derive(Copy, Clone)]
+impl MessageIndex {
+    /// Build a new message index
+    pub fn new(
+        digest: String,
+        receiver_time: usize,
+        sender_time: usize,
+        pubsub_topic: WakuPubSubTopic,
+    ) -> Self {
+        Self {
+            digest,
+            receiver_time,
+            sender_time,
+            pubsub_topic,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Encoding {
     Proto,
     Rlp,
@@ -169,7 +328,37 @@ impl FromStr for Encoding {
 }
 
 impl RegexRepresentation for Encoding {
-    const REGEX: &'static str = r"\w";
+    const REGEX: &'static str = r"\w+";
+}
+
+/// Derive the pubsub topic a `content_topic` autosharding into `shard_count` shards of
+/// cluster `cluster_id` would be published/subscribed on.
+///
+/// Implements the WAKU2 RELAY sharding algorithm (generation 0): the content topic's
+/// `application_name` and decimal `version` are hashed with SHA-256, the last 8 bytes of the
+/// digest are read as a big-endian `u64`, and the shard is that value modulo `shard_count`.
+///
+/// Fails if `shard_count` is `0` or greater than `u16::MAX`, since [`WakuPubSubTopic::Static`]'s
+/// `shard` cannot represent a wider range.
+pub fn autoshard_pubsub_topic(
+    content_topic: &WakuContentTopic,
+    cluster_id: u16,
+    shard_count: u32,
+) -> Result<WakuPubSubTopic> {
+    if shard_count == 0 {
+        return Err("shard_count must be non-zero".to_string());
+    }
+    if shard_count > u16::MAX as u32 {
+        return Err(format!("shard_count must not be greater than {}", u16::MAX));
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(content_topic.application_name.as_bytes());
+    hasher.update(content_topic.version.to_string().as_bytes());
+    let digest = hasher.finalize();
+    let mut last8 = [0u8; 8];
+    last8.copy_from_slice(&digest[digest.len() - 8..]);
+    let shard = (u64::from_be_bytes(last8) % shard_count as u64) as u16;
+    Ok(WakuPubSubTopic::Static { cluster_id, shard })
 }
 
 #[derive(Clone)]
@@ -235,35 +424,50 @@ impl<'de> Deserialize<'de> for WakuContentTopic {
     }
 }
 
-#[derive(Clone)]
-pub struct WakuPubSubTopic {
-    topic_name: String,
-    encoding: Encoding,
+/// A Waku pubsub topic, either the legacy named form or a static/auto shard
+/// as per the [sharding specification](https://rfc.vac.dev/spec/51/)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WakuPubSubTopic {
+    /// Legacy named topic, `/waku/2/{topic_name}/{encoding}`
+    Named {
+        topic_name: String,
+        encoding: Encoding,
+    },
+    /// Static/auto shard topic, `/waku/2/rs/{cluster_id}/{shard}`
+    Static { cluster_id: u16, shard: u16 },
 }
 
 impl FromStr for WakuPubSubTopic {
     type Err = String;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        if let Ok((topic_name, encoding)) = scanf!(s, "/waku/v2/{}/{}", String, Encoding) {
-            Ok(WakuPubSubTopic {
+        if let Ok((cluster_id, shard)) = scanf!(s, "/waku/2/rs/{}/{}", u16, u16) {
+            return Ok(WakuPubSubTopic::Static { cluster_id, shard });
+        }
+        if let Ok((topic_name, encoding)) = scanf!(s, "/waku/2/{}/{}", String, Encoding) {
+            return Ok(WakuPubSubTopic::Named {
                 topic_name,
                 encoding,
-            })
-        } else {
-            Err(
-                format!(
-                    "Wrong pub-sub topic format. Should be `/waku/2/{{topic-name}}/{{encoding}}`. Got: {}",
-                    s
-                )
-            )
+            });
         }
+        Err(format!(
+            "Wrong pub-sub topic format. Should be `/waku/2/{{topic-name}}/{{encoding}}` or `/waku/2/rs/{{cluster-id}}/{{shard}}`. Got: {}",
+            s
+        ))
     }
 }
 
 impl Display for WakuPubSubTopic {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "/waku/2/{}/{}", self.topic_name, self.encoding)
+        match self {
+            WakuPubSubTopic::Named {
+                topic_name,
+                encoding,
+            } => write!(f, "/waku/2/{}/{}", topic_name, encoding),
+            WakuPubSubTopic::Static { cluster_id, shard } => {
+                write!(f, "/waku/2/rs/{}/{}", cluster_id, shard)
+            }
+        }
     }
 }
 
@@ -287,3 +491,161 @@ impl<'de> Deserialize<'de> for WakuPubSubTopic {
             .map_err(D::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_query_with_cursor_creates_paging_options_when_absent() {
+        let cursor = MessageIndex::new(
+            "0x1234".to_string(),
+            1,
+            1,
+            "/waku/2/default-waku/proto".parse().unwrap(),
+        );
+        let query =
+            StoreQuery::new(None, Vec::new(), None, None, None).with_cursor(Some(cursor.clone()));
+        let serialized = serde_json::to_value(&query).unwrap();
+        let paging_options = &serialized["pagingOptions"];
+        assert!(!paging_options.is_null());
+        assert_eq!(paging_options["cursor"]["digest"], "0x1234");
+        assert_eq!(paging_options["forward"], true);
+    }
+
+    #[test]
+    fn autoshard_pubsub_topic_matches_known_vector() {
+        let content_topic: WakuContentTopic = "/toychat/1/huilong/proto".parse().unwrap();
+        assert_eq!(
+            autoshard_pubsub_topic(&content_topic, 1, 8).unwrap(),
+            WakuPubSubTopic::Static {
+                cluster_id: 1,
+                shard: 3
+            }
+        );
+        assert_eq!(
+            autoshard_pubsub_topic(&content_topic, 1, 1).unwrap(),
+            WakuPubSubTopic::Static {
+                cluster_id: 1,
+                shard: 0
+            }
+        );
+    }
+
+    #[test]
+    fn autoshard_pubsub_topic_rejects_zero_shard_count() {
+        let content_topic: WakuContentTopic = "/toychat/1/huilong/proto".parse().unwrap();
+        assert_eq!(
+            autoshard_pubsub_topic(&content_topic, 1, 0).unwrap_err(),
+            "shard_count must be non-zero"
+        );
+    }
+
+    #[test]
+    fn autoshard_pubsub_topic_rejects_overflowing_shard_count() {
+        let content_topic: WakuContentTopic = "/toychat/1/huilong/proto".parse().unwrap();
+        assert_eq!(
+            autoshard_pubsub_topic(&content_topic, 1, u16::MAX as u32 + 1).unwrap_err(),
+            format!("shard_count must not be greater than {}", u16::MAX)
+        );
+    }
+
+    #[test]
+    fn named_pubsub_topic_roundtrips_through_display_and_serde() {
+        let topic: WakuPubSubTopic = "/waku/2/default-waku/proto".parse().unwrap();
+        assert_eq!(
+            topic,
+            WakuPubSubTopic::Named {
+                topic_name: "default-waku".to_string(),
+                encoding: Encoding::Proto
+            }
+        );
+        assert_eq!(topic.to_string(), "/waku/2/default-waku/proto");
+        let serialized = serde_json::to_string(&topic).unwrap();
+        assert_eq!(serialized, "\"/waku/2/default-waku/proto\"");
+        let deserialized: WakuPubSubTopic = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, topic);
+    }
+
+    #[test]
+    fn static_pubsub_topic_roundtrips_through_display_and_serde() {
+        let topic: WakuPubSubTopic = "/waku/2/rs/1/3".parse().unwrap();
+        assert_eq!(
+            topic,
+            WakuPubSubTopic::Static {
+                cluster_id: 1,
+                shard: 3
+            }
+        );
+        assert_eq!(topic.to_string(), "/waku/2/rs/1/3");
+        let serialized = serde_json::to_string(&topic).unwrap();
+        assert_eq!(serialized, "\"/waku/2/rs/1/3\"");
+        let deserialized: WakuPubSubTopic = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, topic);
+    }
+
+    fn decoded_payload(public_key: Option<&str>, signature: Option<&str>) -> DecodedPayload {
+        serde_json::from_value(serde_json::json!({
+            "publicKey": public_key,
+            "signature": signature,
+            "data": "aGVsbG8=",
+            "padding": "",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn decoded_payload_data_bytes_decodes_base64() {
+        let payload = decoded_payload(None, None);
+        assert_eq!(payload.data_bytes().unwrap(), b"hello");
+    }
+
+    #[test]
+    fn decoded_payload_data_bytes_rejects_invalid_base64() {
+        let mut payload = decoded_payload(None, None);
+        payload.data = "not valid base64!".to_string();
+        assert!(payload.data_bytes().is_err());
+    }
+
+    #[test]
+    fn decoded_payload_public_key_bytes_is_none_when_absent() {
+        let payload = decoded_payload(None, None);
+        assert_eq!(payload.public_key_bytes().unwrap(), None);
+    }
+
+    #[test]
+    fn decoded_payload_public_key_bytes_decodes_0x_prefixed_hex() {
+        let payload = decoded_payload(Some("0x1234"), None);
+        assert_eq!(payload.public_key_bytes().unwrap(), Some(vec![0x12, 0x34]));
+    }
+
+    #[test]
+    fn decoded_payload_public_key_bytes_decodes_bare_hex() {
+        let payload = decoded_payload(Some("1234"), None);
+        assert_eq!(payload.public_key_bytes().unwrap(), Some(vec![0x12, 0x34]));
+    }
+
+    #[test]
+    fn decoded_payload_public_key_bytes_rejects_invalid_hex() {
+        let payload = decoded_payload(Some("0xzz"), None);
+        assert!(payload.public_key_bytes().is_err());
+    }
+
+    #[test]
+    fn decoded_payload_signature_bytes_is_none_when_absent() {
+        let payload = decoded_payload(None, None);
+        assert_eq!(payload.signature_bytes().unwrap(), None);
+    }
+
+    #[test]
+    fn decoded_payload_signature_bytes_decodes_0x_prefixed_hex() {
+        let payload = decoded_payload(None, Some("0xabcd"));
+        assert_eq!(payload.signature_bytes().unwrap(), Some(vec![0xab, 0xcd]));
+    }
+
+    #[test]
+    fn decoded_payload_signature_bytes_rejects_invalid_hex() {
+        let payload = decoded_payload(None, Some("not-hex"));
+        assert!(payload.signature_bytes().is_err());
+    }
+}
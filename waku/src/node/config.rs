@@ -0,0 +1,25 @@
+//! Configuration used to spawn a new Waku node
+
+use serde::Serialize;
+
+/// Waku node configuration, used to spawn a node with [`waku_new`](super::waku_new)
+///
+/// as per the [specification](https://rfc.vac.dev/spec/36/#jsonconfig-type)
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakuNodeConfig {
+    /// Listening IP address. Default `0.0.0.0`
+    pub host: Option<String>,
+    /// Libp2p TCP listening port. Default `60000`. Use `0` for random
+    pub port: Option<usize>,
+    /// External address to advertise to other peers
+    pub advertise_addr: Option<String>,
+    /// Enable relay protocol. Default `true`
+    pub relay: Option<bool>,
+    /// Cluster id used for static/auto sharding. Default `0`
+    pub cluster_id: Option<u16>,
+    /// Number of shards in the cluster, used to derive autosharded pubsub topics.
+    /// Only meaningful together with [`cluster_id`](Self::cluster_id)
+    #[serde(skip_serializing)]
+    pub shard_count: Option<u32>,
+}
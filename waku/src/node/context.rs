@@ -0,0 +1,26 @@
+//! Opaque handle to a native `libwaku` node instance.
+
+use std::os::raw::c_void;
+
+/// Opaque pointer to a `libwaku` node instance.
+///
+/// Newer versions of `libwaku` take this context as the first argument of every
+/// FFI call, which is what allows several nodes to coexist in the same process.
+/// [`WakuNodeHandle`](super::WakuNodeHandle) owns one of these and threads it through
+/// to the `management`/`peers`/`relay`/`lightpush` wrappers it delegates to.
+#[derive(Clone, Copy)]
+pub(crate) struct WakuNodeContext(*mut c_void);
+
+/// The pointer is only ever handed to `libwaku`, which is documented to be thread safe.
+unsafe impl Send for WakuNodeContext {}
+unsafe impl Sync for WakuNodeContext {}
+
+impl WakuNodeContext {
+    pub(crate) fn new(ptr: *mut c_void) -> Self {
+        Self(ptr)
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut c_void {
+        self.0
+    }
+}
@@ -0,0 +1,65 @@
+//! Bridges `libwaku`'s native event callback into typed Rust [`Signal`]s
+
+use serde::Deserialize;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+
+use super::context::WakuNodeContext;
+use crate::general::{MessageId, WakuMessage, WakuPubSubTopic};
+
+mod ffi {
+    use std::os::raw::{c_char, c_void};
+
+    pub type EventCallback = extern "C" fn(*const c_char, *mut c_void);
+
+    extern "C" {
+        pub fn waku_set_event_callback(ctx: *mut c_void, cb: EventCallback, user_data: *mut c_void);
+    }
+}
+
+/// An asynchronous event delivered by `libwaku` through the callback registered with
+/// [`set_event_callback`](super::WakuNodeHandle::set_event_callback)
+#[derive(Clone, Deserialize)]
+#[serde(tag = "type", content = "event", rename_all = "camelCase")]
+pub enum Signal {
+    /// A new message was received on a subscribed pubsub topic
+    Message(MessageEvent),
+}
+
+/// Payload of a [`Signal::Message`] event
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageEvent {
+    /// Id of the received message
+    pub message_id: MessageId,
+    /// Pubsub topic the message was received on
+    pub pubsub_topic: WakuPubSubTopic,
+    /// The received message itself
+    pub waku_message: WakuMessage,
+}
+
+/// Native trampoline handed to `libwaku`: decodes the JSON event and forwards it to the
+/// boxed Rust callback stashed behind `user_data`
+extern "C" fn trampoline(data: *const c_char, user_data: *mut c_void) {
+    let data = unsafe { CStr::from_ptr(data) }
+        .to_str()
+        .expect("event payload is always valid utf8");
+    let Ok(signal) = serde_json::from_str::<Signal>(data) else {
+        return;
+    };
+    let callback = unsafe { &*(user_data as *const Box<dyn Fn(Signal) + Send>) };
+    callback(signal);
+}
+
+/// Register `callback` to be invoked for every [`Signal`] this node context emits
+///
+/// wrapper around the `waku_set_event_callback` FFI call. The callback is boxed and leaked
+/// for the lifetime of the process, mirroring that `libwaku` has no API to unregister it.
+pub(crate) fn waku_set_event_callback(
+    ctx: &WakuNodeContext,
+    callback: impl Fn(Signal) + Send + 'static,
+) {
+    let callback: Box<Box<dyn Fn(Signal) + Send>> = Box::new(Box::new(callback));
+    let user_data = Box::into_raw(callback) as *mut c_void;
+    unsafe { ffi::waku_set_event_callback(ctx.as_ptr(), trampoline, user_data) };
+}
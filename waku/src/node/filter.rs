@@ -0,0 +1,62 @@
+//! Waku Filter protocol
+//!
+//! wraps the [`filter`](https://rfc.vac.dev/spec/36/#filter) FFI calls
+
+use std::time::Duration;
+
+use super::context::WakuNodeContext;
+use super::utils::{decode_response, timeout_to_ms, to_c_string};
+use crate::general::{FilterSubscription, Result};
+
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        pub fn waku_filter_subscribe(
+            ctx: *mut c_void,
+            subscription: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_filter_unsubscribe(
+            ctx: *mut c_void,
+            subscription: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+    }
+}
+
+/// Create a filter subscription to receive messages matching `sub` without running full relay
+///
+/// wrapper around the `waku_filter_subscribe` FFI call
+pub(crate) fn waku_filter_subscribe(
+    ctx: &WakuNodeContext,
+    sub: &FilterSubscription,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let sub = to_c_string(serde_json::to_string(sub).expect("subscription is always valid json"));
+    unsafe {
+        decode_response(ffi::waku_filter_subscribe(
+            ctx.as_ptr(),
+            sub.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Remove a filter subscription, no more messages matching `sub` will be received
+///
+/// wrapper around the `waku_filter_unsubscribe` FFI call
+pub(crate) fn waku_filter_unsubscribe(
+    ctx: &WakuNodeContext,
+    sub: &FilterSubscription,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let sub = to_c_string(serde_json::to_string(sub).expect("subscription is always valid json"));
+    unsafe {
+        decode_response(ffi::waku_filter_unsubscribe(
+            ctx.as_ptr(),
+            sub.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
@@ -0,0 +1,141 @@
+//! Waku Lightpush protocol
+//!
+//! wraps the [`lightpush`](https://rfc.vac.dev/spec/36/#lightpush) FFI calls
+
+use aes_gcm::{Aes256Gcm, Key};
+use libsecp256k1::{PublicKey, SecretKey};
+use std::time::Duration;
+
+use super::context::WakuNodeContext;
+use super::relay::pubsub_topic_c_string;
+use super::utils::{decode_response, timeout_to_ms, to_c_string};
+use crate::general::{MessageId, PeerId, Result, WakuMessage, WakuPubSubTopic};
+
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        pub fn waku_lightpush_publish(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+            message: *const c_char,
+            peer_id: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_lightpush_publish_enc_asymmetric(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+            message: *const c_char,
+            peer_id: *const c_char,
+            public_key: *const c_char,
+            signing_key: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_lightpush_publish_enc_symmetric(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+            message: *const c_char,
+            peer_id: *const c_char,
+            symmetric_key: *const c_char,
+            signing_key: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+    }
+}
+
+/// Publish a message using Waku Lightpush, disseminated through `peer_id`
+///
+/// wrapper around the `waku_lightpush_publish` FFI call
+pub(crate) fn waku_lightpush_publish(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    pubsub_topic: Option<WakuPubSubTopic>,
+    peer_id: PeerId,
+    timeout: Option<Duration>,
+) -> Result<MessageId> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    let peer_id = to_c_string(peer_id);
+    unsafe {
+        decode_response(ffi::waku_lightpush_publish(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+            message.as_ptr(),
+            peer_id.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Optionally sign, encrypt using asymmetric encryption and publish a message using Lightpush,
+/// disseminated through `peer_id`
+///
+/// wrapper around the `waku_lightpush_publish_enc_asymmetric` FFI call
+pub(crate) fn waku_lightpush_publish_encrypt_asymmetric(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    pubsub_topic: Option<WakuPubSubTopic>,
+    peer_id: PeerId,
+    public_key: &PublicKey,
+    signing_key: Option<&SecretKey>,
+    timeout: Option<Duration>,
+) -> Result<MessageId> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    let peer_id = to_c_string(peer_id);
+    let public_key = to_c_string(hex::encode(public_key.serialize()));
+    let signing_key = to_c_string(
+        signing_key
+            .map(|key| hex::encode(key.serialize()))
+            .unwrap_or_default(),
+    );
+    unsafe {
+        decode_response(ffi::waku_lightpush_publish_enc_asymmetric(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+            message.as_ptr(),
+            peer_id.as_ptr(),
+            public_key.as_ptr(),
+            signing_key.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Optionally sign, encrypt using symmetric encryption and publish a message using Lightpush,
+/// disseminated through `peer_id`
+///
+/// wrapper around the `waku_lightpush_publish_enc_symmetric` FFI call
+pub(crate) fn waku_lightpush_publish_encrypt_symmetric(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    pubsub_topic: Option<WakuPubSubTopic>,
+    peer_id: PeerId,
+    symmetric_key: &Key<Aes256Gcm>,
+    signing_key: Option<&SecretKey>,
+    timeout: Option<Duration>,
+) -> Result<MessageId> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    let peer_id = to_c_string(peer_id);
+    let symmetric_key = to_c_string(hex::encode(symmetric_key));
+    let signing_key = to_c_string(
+        signing_key
+            .map(|key| hex::encode(key.serialize()))
+            .unwrap_or_default(),
+    );
+    unsafe {
+        decode_response(ffi::waku_lightpush_publish_enc_symmetric(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+            message.as_ptr(),
+            peer_id.as_ptr(),
+            symmetric_key.as_ptr(),
+            signing_key.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
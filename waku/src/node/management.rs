@@ -0,0 +1,64 @@
+//! Waku node lifecycle management: create, start, stop and introspect a node
+//!
+//! wraps the [`management`](https://rfc.vac.dev/spec/36/#node-management) FFI calls
+
+use multiaddr::Multiaddr;
+use std::os::raw::c_void;
+
+use super::config::WakuNodeConfig;
+use super::context::WakuNodeContext;
+use super::utils::{decode_response, to_c_string};
+use crate::general::{PeerId, Result};
+
+mod ffi {
+    use std::os::raw::{c_char, c_void};
+
+    extern "C" {
+        pub fn waku_new(config: *const c_char) -> *mut c_void;
+        pub fn waku_start(ctx: *mut c_void) -> *mut c_char;
+        pub fn waku_stop(ctx: *mut c_void) -> *mut c_char;
+        pub fn waku_peer_id(ctx: *mut c_void) -> *mut c_char;
+        pub fn waku_listen_addresses(ctx: *mut c_void) -> *mut c_char;
+    }
+}
+
+/// Spawn a new node context, configured with `config` (default configuration if `None`)
+///
+/// wrapper around the `waku_new` FFI call
+pub(crate) fn waku_new(config: Option<WakuNodeConfig>) -> Result<WakuNodeContext> {
+    let config = config.unwrap_or_default();
+    let config = to_c_string(serde_json::to_string(&config).expect("config is always valid json"));
+    let ctx: *mut c_void = unsafe { ffi::waku_new(config.as_ptr()) };
+    if ctx.is_null() {
+        return Err("waku_new returned a null node context".to_string());
+    }
+    Ok(WakuNodeContext::new(ctx))
+}
+
+/// Start a Waku node mounting all the protocols that were enabled during its instantiation
+///
+/// wrapper around the `waku_start` FFI call
+pub(crate) fn waku_start(ctx: &WakuNodeContext) -> Result<()> {
+    unsafe { decode_response(ffi::waku_start(ctx.as_ptr())) }
+}
+
+/// Stop a Waku node
+///
+/// wrapper around the `waku_stop` FFI call
+pub(crate) fn waku_stop(ctx: &WakuNodeContext) -> Result<()> {
+    unsafe { decode_response(ffi::waku_stop(ctx.as_ptr())) }
+}
+
+/// If the execution is successful, the result is the peer ID as a string (base58 encoded)
+///
+/// wrapper around the `waku_peer_id` FFI call
+pub(crate) fn waku_peer_id(ctx: &WakuNodeContext) -> Result<PeerId> {
+    unsafe { decode_response(ffi::waku_peer_id(ctx.as_ptr())) }
+}
+
+/// Get the multiaddresses the Waku node is listening to
+///
+/// wrapper around the `waku_listen_addresses` FFI call
+pub(crate) fn waku_listen_addresses(ctx: &WakuNodeContext) -> Result<Vec<Multiaddr>> {
+    unsafe { decode_response(ffi::waku_listen_addresses(ctx.as_ptr())) }
+}
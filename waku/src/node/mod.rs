@@ -1,26 +1,33 @@
 mod config;
+mod context;
+mod events;
+mod filter;
 mod lightpush;
 mod management;
 mod peers;
 mod relay;
+mod store;
+mod utils;
 
 // std
 use aes_gcm::{Aes256Gcm, Key};
 use libsecp256k1::{PublicKey, SecretKey};
 use multiaddr::Multiaddr;
-use std::marker::PhantomData;
-use std::sync::Mutex;
 use std::time::Duration;
 // crates
 // internal
-use crate::general::{MessageId, PeerId, Result, WakuMessage, WakuPubSubTopic};
+use crate::general::{
+    autoshard_pubsub_topic, DecodedPayload, FilterSubscription, MessageId, PeerId, Result,
+    StoreQuery, StoreResponse, WakuContentTopic, WakuMessage, WakuPubSubTopic,
+};
+use context::WakuNodeContext;
 
 pub use config::WakuNodeConfig;
+pub use events::Signal;
 pub use peers::{Protocol, WakuPeerData, WakuPeers};
-pub use relay::{waku_create_content_topic, waku_create_pubsub_topic, waku_dafault_pubsub_topic};
-
-/// Shared flag to check if a waku node is already running in the current process
-static WAKU_NODE_INITIALIZED: Mutex<bool> = Mutex::new(false);
+pub use relay::{
+    waku_create_content_topic, waku_create_pubsub_topic, waku_dafault_pubsub_topic, DecryptionKey,
+};
 
 /// Marker trait to disallow undesired waku node states in the handle
 pub trait WakuNodeState {}
@@ -34,9 +41,14 @@ pub struct Running;
 impl WakuNodeState for Initialized {}
 impl WakuNodeState for Running {}
 
-pub struct WakuNodeHandle<State: WakuNodeState>(PhantomData<State>);
+pub struct WakuNodeHandle<State: WakuNodeState> {
+    ctx: WakuNodeContext,
+    /// `(cluster_id, shard_count)` this node was configured with, if autosharding is enabled
+    autosharding: Option<(u16, u32)>,
+    _state: std::marker::PhantomData<State>,
+}
 
-/// We do not have any inner state, so the handle should be safe to be send among threads.
+/// The context is only ever handed to `libwaku`, which is documented to be thread safe.
 unsafe impl<State: WakuNodeState> Send for WakuNodeHandle<State> {}
 
 /// References to the handle are safe to share, as they do not mutate the handle itself and
@@ -44,49 +56,50 @@ unsafe impl<State: WakuNodeState> Send for WakuNodeHandle<State> {}
 unsafe impl<State: WakuNodeState> Sync for WakuNodeHandle<State> {}
 
 impl<State: WakuNodeState> WakuNodeHandle<State> {
+    fn with_ctx(ctx: WakuNodeContext, autosharding: Option<(u16, u32)>) -> Self {
+        Self {
+            ctx,
+            autosharding,
+            _state: Default::default(),
+        }
+    }
+
     /// If the execution is successful, the result is the peer ID as a string (base58 encoded)
     ///
     /// wrapper around [`management::waku_peer_id`]
     pub fn peer_id(&self) -> Result<PeerId> {
-        management::waku_peer_id()
+        management::waku_peer_id(&self.ctx)
     }
 
     /// Get the multiaddresses the Waku node is listening to
     ///
     /// wrapper around [`management::waku_listen_addresses`]
     pub fn listen_addresses(&self) -> Result<Vec<Multiaddr>> {
-        management::waku_listen_addresses()
+        management::waku_listen_addresses(&self.ctx)
     }
 
     /// Add a node multiaddress and protocol to the waku node’s peerstore
     ///
     /// wrapper around [`peers::waku_add_peers`]
     pub fn add_peer(&self, address: Multiaddr, protocol_id: usize) -> Result<PeerId> {
-        peers::waku_add_peers(address, protocol_id)
+        peers::waku_add_peers(&self.ctx, address, protocol_id)
     }
 }
 
-fn stop_node() -> Result<()> {
-    let mut node_initialized = WAKU_NODE_INITIALIZED
-        .lock()
-        .expect("Access to the mutex at some point");
-    *node_initialized = false;
-    management::waku_stop().map(|_| ())
-}
-
 impl WakuNodeHandle<Initialized> {
     /// Start a Waku node mounting all the protocols that were enabled during the Waku node instantiation
     ///
     /// wrapper around [`management::waku_start`]
     pub fn start(self) -> Result<WakuNodeHandle<Running>> {
-        management::waku_start().map(|_| WakuNodeHandle(Default::default()))
+        management::waku_start(&self.ctx)
+            .map(|_| WakuNodeHandle::with_ctx(self.ctx, self.autosharding))
     }
 
     /// Stops a Waku node
     ///
     /// internally uses [`management::waku_stop`]
     pub fn stop(self) -> Result<()> {
-        stop_node()
+        management::waku_stop(&self.ctx)
     }
 }
 
@@ -95,7 +108,7 @@ impl WakuNodeHandle<Running> {
     ///
     /// internally uses [`management::waku_stop`]
     pub fn stop(self) -> Result<()> {
-        stop_node()
+        management::waku_stop(&self.ctx)
     }
 
     /// Dial peer using a multiaddress
@@ -109,38 +122,60 @@ impl WakuNodeHandle<Running> {
         address: Multiaddr,
         timeout: Option<Duration>,
     ) -> Result<()> {
-        peers::waku_connect_peer_with_address(address, timeout)
+        peers::waku_connect_peer_with_address(&self.ctx, address, timeout)
     }
 
     /// Dial peer using its peer ID
     ///
     /// wrapper around [`peers::waku_connect_peer_with_id`]
     pub fn connect_peer_with_id(&self, peer_id: PeerId, timeout: Option<Duration>) -> Result<()> {
-        peers::waku_connect_peer_with_id(peer_id, timeout)
+        peers::waku_connect_peer_with_id(&self.ctx, peer_id, timeout)
     }
 
     /// Disconnect a peer using its peerID
     ///
     /// wrapper around [`peers::waku_disconnect_peer_with_id`]
     pub fn disconnect_peer_with_id(&self, peer_id: PeerId) -> Result<()> {
-        peers::waku_disconnect_peer_with_id(peer_id)
+        peers::waku_disconnect_peer_with_id(&self.ctx, peer_id)
     }
 
     /// Get number of connected peers
     ///
     /// wrapper around [`peers::waku_peer_count`]
     pub fn peer_count(&self) -> Result<usize> {
-        peers::waku_peer_count()
+        peers::waku_peer_count(&self.ctx)
     }
 
     /// Retrieve the list of peers known by the Waku node
     ///
     /// wrapper around [`peers::waku_peers`]
     pub fn peers(&self) -> Result<WakuPeers> {
-        peers::waku_peers()
+        peers::waku_peers(&self.ctx)
     }
 
-    /// Publish a message using Waku Relay
+    /// Derive the pubsub topic to act on: `pubsub_topic` if set, otherwise the autosharded
+    /// topic for `content_topic` if this node was configured with a cluster id and shard
+    /// count, otherwise the legacy default pubsub topic
+    fn resolve_pubsub_topic(
+        &self,
+        pubsub_topic: Option<WakuPubSubTopic>,
+        content_topic: &WakuContentTopic,
+    ) -> WakuPubSubTopic {
+        if let Some(pubsub_topic) = pubsub_topic {
+            return pubsub_topic;
+        }
+        match self.autosharding {
+            // `cluster_id`/`shard_count` were already validated in `waku_new`
+            Some((cluster_id, shard_count)) => {
+                autoshard_pubsub_topic(content_topic, cluster_id, shard_count)
+                    .expect("shard_count was already validated in waku_new")
+            }
+            None => relay::waku_dafault_pubsub_topic(),
+        }
+    }
+
+    /// Publish a message using Waku Relay. If `pubsub_topic` is `None`, it is derived from the
+    /// message's content topic through autosharding when this node was configured for it
     ///
     /// wrapper around [`relay::waku_relay_publish_message`]
     pub fn relay_publish_message(
@@ -149,7 +184,8 @@ impl WakuNodeHandle<Running> {
         pubsub_topic: Option<WakuPubSubTopic>,
         timeout: Duration,
     ) -> Result<MessageId> {
-        relay::waku_relay_publish_message(message, pubsub_topic, timeout)
+        let pubsub_topic = self.resolve_pubsub_topic(pubsub_topic, message.content_topic());
+        relay::waku_relay_publish_message(&self.ctx, message, Some(pubsub_topic), timeout)
     }
 
     /// Optionally sign, encrypt using asymmetric encryption and publish a message using Waku Relay
@@ -164,6 +200,7 @@ impl WakuNodeHandle<Running> {
         timeout: Duration,
     ) -> Result<MessageId> {
         relay::waku_relay_publish_encrypt_asymmetric(
+            &self.ctx,
             message,
             pubsub_topic,
             public_key,
@@ -184,6 +221,7 @@ impl WakuNodeHandle<Running> {
         timeout: Duration,
     ) -> Result<MessageId> {
         relay::waku_relay_publish_encrypt_symmetric(
+            &self.ctx,
             message,
             pubsub_topic,
             symmetric_key,
@@ -196,35 +234,204 @@ impl WakuNodeHandle<Running> {
     ///
     /// wrapper around [`relay::waku_enough_peers`]
     pub fn relay_enough_peers(&self, pubsub_topic: Option<WakuPubSubTopic>) -> Result<bool> {
-        relay::waku_enough_peers(pubsub_topic)
+        relay::waku_enough_peers(&self.ctx, pubsub_topic)
     }
 
-    /// Subscribe to a Waku Relay pubsub topic to receive messages
+    /// Subscribe to a Waku Relay pubsub topic to receive messages. If `pubsub_topic` is `None`,
+    /// it is derived from `content_topic` through autosharding when this node was configured for it.
+    /// Fails if neither is given and this node is autosharding-configured, since the legacy default
+    /// pubsub topic is unlikely to be the topic content is actually autosharded onto
     ///
     /// wrapper around [`relay::waku_relay_subscribe`]
-    pub fn relay_subscribe(&self, pubsub_topic: Option<WakuPubSubTopic>) -> Result<()> {
-        relay::waku_relay_subscribe(pubsub_topic)
+    pub fn relay_subscribe(
+        &self,
+        pubsub_topic: Option<WakuPubSubTopic>,
+        content_topic: Option<&WakuContentTopic>,
+    ) -> Result<()> {
+        let pubsub_topic = match (pubsub_topic, content_topic) {
+            (Some(pubsub_topic), _) => pubsub_topic,
+            (None, Some(content_topic)) => self.resolve_pubsub_topic(None, content_topic),
+            (None, None) if self.autosharding.is_some() => {
+                return Err(
+                    "a pubsub_topic or content_topic is required to subscribe on an autosharding-configured node"
+                        .to_string(),
+                );
+            }
+            (None, None) => relay::waku_dafault_pubsub_topic(),
+        };
+        relay::waku_relay_subscribe(&self.ctx, Some(pubsub_topic))
     }
 
     /// Closes the pubsub subscription to a pubsub topic. No more messages will be received from this pubsub topic
     ///
     /// wrapper around [`relay::waku_relay_unsubscribe`]
     pub fn relay_unsubscribe(&self, pubsub_topic: Option<WakuPubSubTopic>) -> Result<()> {
-        relay::waku_relay_unsubscribe(pubsub_topic)
+        relay::waku_relay_unsubscribe(&self.ctx, pubsub_topic)
+    }
+
+    /// Decrypt a received encrypted message with `key`, closing the loop with
+    /// [`relay_publish_encrypt_asymmetric`](Self::relay_publish_encrypt_asymmetric)/
+    /// [`relay_publish_encrypt_symmetric`](Self::relay_publish_encrypt_symmetric)
+    ///
+    /// wrapper around [`relay::waku_decode_payload`]
+    pub fn decode_payload(
+        &self,
+        message: &WakuMessage,
+        key: DecryptionKey,
+    ) -> Result<DecodedPayload> {
+        relay::waku_decode_payload(&self.ctx, message, key)
+    }
+
+    /// Retrieve historical messages stored by a Store node matching `query`
+    ///
+    /// wrapper around [`store::waku_store_query`]
+    pub fn store_query(
+        &self,
+        query: StoreQuery,
+        peer_id: PeerId,
+        timeout: Option<Duration>,
+    ) -> Result<StoreResponse> {
+        store::waku_store_query(&self.ctx, &query, peer_id, timeout)
+    }
+
+    /// Retrieve all historical messages matching `query`, transparently following the
+    /// `paging_options` cursor returned by the store node until it is exhausted
+    pub fn store_query_all(
+        &self,
+        query: StoreQuery,
+        peer_id: PeerId,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<WakuMessage>> {
+        let mut messages = Vec::new();
+        let mut query = query;
+        loop {
+            let response = store::waku_store_query(&self.ctx, &query, peer_id.clone(), timeout)?;
+            let cursor = response.cursor().cloned();
+            messages.extend(response.messages().iter().cloned());
+            match cursor {
+                Some(cursor) => query = query.with_cursor(Some(cursor)),
+                None => break,
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Create a Filter subscription to receive messages matching `sub` without running full relay
+    ///
+    /// wrapper around [`filter::waku_filter_subscribe`]
+    pub fn filter_subscribe(
+        &self,
+        sub: FilterSubscription,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        filter::waku_filter_subscribe(&self.ctx, &sub, timeout)
+    }
+
+    /// Remove a Filter subscription, no more messages matching `sub` will be received
+    ///
+    /// wrapper around [`filter::waku_filter_unsubscribe`]
+    pub fn filter_unsubscribe(
+        &self,
+        sub: FilterSubscription,
+        timeout: Option<Duration>,
+    ) -> Result<()> {
+        filter::waku_filter_unsubscribe(&self.ctx, &sub, timeout)
+    }
+
+    /// Register `callback` to be invoked for every [`Signal`] this node emits, such as
+    /// messages received through an active Filter or Relay subscription
+    ///
+    /// wrapper around [`events::waku_set_event_callback`]
+    pub fn set_event_callback(&self, callback: impl Fn(Signal) + Send + 'static) {
+        events::waku_set_event_callback(&self.ctx, callback)
+    }
+
+    /// Publish a message using Lightpush, disseminated through `peer_id`, without running full
+    /// relay. If `pubsub_topic` is `None`, it is derived from the message's content topic through
+    /// autosharding when this node was configured for it
+    ///
+    /// wrapper around [`lightpush::waku_lightpush_publish`]
+    pub fn lightpush_publish(
+        &self,
+        message: &WakuMessage,
+        pubsub_topic: Option<WakuPubSubTopic>,
+        peer_id: PeerId,
+        timeout: Option<Duration>,
+    ) -> Result<MessageId> {
+        let pubsub_topic = self.resolve_pubsub_topic(pubsub_topic, message.content_topic());
+        lightpush::waku_lightpush_publish(&self.ctx, message, Some(pubsub_topic), peer_id, timeout)
+    }
+
+    /// Optionally sign, encrypt using asymmetric encryption and publish a message using Lightpush.
+    /// If `pubsub_topic` is `None`, it is derived from the message's content topic through
+    /// autosharding when this node was configured for it
+    ///
+    /// wrapper around [`lightpush::waku_lightpush_publish_encrypt_asymmetric`]
+    pub fn lightpush_publish_encrypt_asymmetric(
+        &self,
+        message: &WakuMessage,
+        pubsub_topic: Option<WakuPubSubTopic>,
+        peer_id: PeerId,
+        public_key: &PublicKey,
+        signing_key: Option<&SecretKey>,
+        timeout: Option<Duration>,
+    ) -> Result<MessageId> {
+        let pubsub_topic = self.resolve_pubsub_topic(pubsub_topic, message.content_topic());
+        lightpush::waku_lightpush_publish_encrypt_asymmetric(
+            &self.ctx,
+            message,
+            Some(pubsub_topic),
+            peer_id,
+            public_key,
+            signing_key,
+            timeout,
+        )
+    }
+
+    /// Optionally sign, encrypt using symmetric encryption and publish a message using Lightpush.
+    /// If `pubsub_topic` is `None`, it is derived from the message's content topic through
+    /// autosharding when this node was configured for it
+    ///
+    /// wrapper around [`lightpush::waku_lightpush_publish_encrypt_symmetric`]
+    pub fn lightpush_publish_encrypt_symmetric(
+        &self,
+        message: &WakuMessage,
+        pubsub_topic: Option<WakuPubSubTopic>,
+        peer_id: PeerId,
+        symmetric_key: &Key<Aes256Gcm>,
+        signing_key: Option<&SecretKey>,
+        timeout: Option<Duration>,
+    ) -> Result<MessageId> {
+        let pubsub_topic = self.resolve_pubsub_topic(pubsub_topic, message.content_topic());
+        lightpush::waku_lightpush_publish_encrypt_symmetric(
+            &self.ctx,
+            message,
+            Some(pubsub_topic),
+            peer_id,
+            symmetric_key,
+            signing_key,
+            timeout,
+        )
     }
 }
 
-/// Spawn a new Waku node with the givent configuration (default configuration if `None` provided)
+/// Spawn a new Waku node bound to its own context, with the given configuration
+/// (default configuration if `None` provided). Unlike older `libwaku` versions, several
+/// contexts can coexist in the same process, so several nodes can be spawned side by side.
 /// Internally uses [`management::waku_new`]
 pub fn waku_new(config: Option<WakuNodeConfig>) -> Result<WakuNodeHandle<Initialized>> {
-    let mut node_initialized = WAKU_NODE_INITIALIZED
-        .lock()
-        .expect("Access to the mutex at some point");
-    if *node_initialized {
-        return Err("Waku node is already initialized".into());
-    }
-    *node_initialized = true;
-    management::waku_new(config).map(|_| WakuNodeHandle(Default::default()))
+    let autosharding = config
+        .as_ref()
+        .and_then(|config| Some((config.cluster_id?, config.shard_count?)));
+    if let Some((_, shard_count)) = autosharding {
+        if shard_count == 0 {
+            return Err("shard_count must be non-zero".to_string());
+        }
+        if shard_count > u16::MAX as u32 {
+            return Err(format!("shard_count must not be greater than {}", u16::MAX));
+        }
+    }
+    management::waku_new(config).map(|ctx| WakuNodeHandle::with_ctx(ctx, autosharding))
 }
 
 #[cfg(test)]
@@ -232,11 +439,12 @@ mod tests {
     use super::waku_new;
 
     #[test]
-    fn exclusive_running() {
+    fn several_nodes_can_coexist() {
         let handle1 = waku_new(None).unwrap();
-        let handle2 = waku_new(None);
-        assert!(handle2.is_err());
-        let stop_handle = handle1.start().unwrap();
-        stop_handle.stop().unwrap();
+        let handle2 = waku_new(None).unwrap();
+        let stop_handle1 = handle1.start().unwrap();
+        let stop_handle2 = handle2.start().unwrap();
+        stop_handle1.stop().unwrap();
+        stop_handle2.stop().unwrap();
     }
 }
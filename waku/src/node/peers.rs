@@ -0,0 +1,141 @@
+//! Waku node peer management
+//!
+//! wraps the [`peers`](https://rfc.vac.dev/spec/36/#connecting-to-peers) FFI calls
+
+use multiaddr::Multiaddr;
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::context::WakuNodeContext;
+use super::utils::{decode_response, timeout_to_ms, to_c_string};
+use crate::general::{PeerId, Result};
+
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        pub fn waku_add_peer(
+            ctx: *mut c_void,
+            address: *const c_char,
+            protocol_id: c_int,
+        ) -> *mut c_char;
+        pub fn waku_connect(
+            ctx: *mut c_void,
+            address: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_dial_peer_by_id(
+            ctx: *mut c_void,
+            peer_id: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_disconnect(ctx: *mut c_void, peer_id: *const c_char) -> *mut c_char;
+        pub fn waku_peer_cnt(ctx: *mut c_void) -> *mut c_char;
+        pub fn waku_peers(ctx: *mut c_void) -> *mut c_char;
+    }
+}
+
+/// Supported Waku protocols, as reported in [`WakuPeerData`]
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Store,
+    Lightpush,
+    Filter,
+    Relay,
+}
+
+/// Information about a peer known by the Waku node
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakuPeerData {
+    /// The peer id
+    pub peer_id: PeerId,
+    /// The protocols the peer supports
+    pub protocols: Vec<Protocol>,
+    /// The multiaddresses the peer is reachable at
+    pub addrs: Vec<Multiaddr>,
+    /// Connection status with the peer
+    pub connected: bool,
+}
+
+pub type WakuPeers = Vec<WakuPeerData>;
+
+/// Add a node multiaddress and protocol to the waku node's peerstore
+///
+/// wrapper around the `waku_add_peer` FFI call
+pub(crate) fn waku_add_peers(
+    ctx: &WakuNodeContext,
+    address: Multiaddr,
+    protocol_id: usize,
+) -> Result<PeerId> {
+    let address = to_c_string(address.to_string());
+    unsafe {
+        decode_response(ffi::waku_add_peer(
+            ctx.as_ptr(),
+            address.as_ptr(),
+            protocol_id as i32,
+        ))
+    }
+}
+
+/// Dial peer using a multiaddress
+/// If `timeout` as milliseconds doesn't fit into a `i32` it is clamped to [`i32::MAX`]
+/// If the function execution takes longer than `timeout` value, the execution will be canceled and an error returned.
+/// Use 0 for no timeout
+///
+/// wrapper around the `waku_connect` FFI call
+pub(crate) fn waku_connect_peer_with_address(
+    ctx: &WakuNodeContext,
+    address: Multiaddr,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let address = to_c_string(address.to_string());
+    unsafe {
+        decode_response(ffi::waku_connect(
+            ctx.as_ptr(),
+            address.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Dial peer using its peer ID
+///
+/// wrapper around the `waku_dial_peer_by_id` FFI call
+pub(crate) fn waku_connect_peer_with_id(
+    ctx: &WakuNodeContext,
+    peer_id: PeerId,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    let peer_id = to_c_string(peer_id);
+    unsafe {
+        decode_response(ffi::waku_dial_peer_by_id(
+            ctx.as_ptr(),
+            peer_id.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Disconnect a peer using its peerID
+///
+/// wrapper around the `waku_disconnect` FFI call
+pub(crate) fn waku_disconnect_peer_with_id(ctx: &WakuNodeContext, peer_id: PeerId) -> Result<()> {
+    let peer_id = to_c_string(peer_id);
+    unsafe { decode_response(ffi::waku_disconnect(ctx.as_ptr(), peer_id.as_ptr())) }
+}
+
+/// Get number of connected peers
+///
+/// wrapper around the `waku_peer_cnt` FFI call
+pub(crate) fn waku_peer_count(ctx: &WakuNodeContext) -> Result<usize> {
+    unsafe { decode_response(ffi::waku_peer_cnt(ctx.as_ptr())) }
+}
+
+/// Retrieve the list of peers known by the Waku node
+///
+/// wrapper around the `waku_peers` FFI call
+pub(crate) fn waku_peers(ctx: &WakuNodeContext) -> Result<WakuPeers> {
+    unsafe { decode_response(ffi::waku_peers(ctx.as_ptr())) }
+}
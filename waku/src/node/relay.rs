@@ -0,0 +1,271 @@
+//! Waku Relay protocol
+//!
+//! wraps the [`relay`](https://rfc.vac.dev/spec/36/#relay) FFI calls
+
+use aes_gcm::{Aes256Gcm, Key};
+use libsecp256k1::{PublicKey, SecretKey};
+use std::time::Duration;
+
+use super::context::WakuNodeContext;
+use super::utils::{decode_response, to_c_string};
+use crate::general::{
+    DecodedPayload, Encoding, MessageId, Result, WakuContentTopic, WakuMessage, WakuPubSubTopic,
+};
+
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        pub fn waku_relay_publish(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+            message: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_relay_publish_enc_asymmetric(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+            message: *const c_char,
+            public_key: *const c_char,
+            signing_key: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_relay_publish_enc_symmetric(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+            message: *const c_char,
+            symmetric_key: *const c_char,
+            signing_key: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+        pub fn waku_relay_enough_peers(
+            ctx: *mut c_void,
+            pubsub_topic: *const c_char,
+        ) -> *mut c_char;
+        pub fn waku_relay_subscribe(ctx: *mut c_void, pubsub_topic: *const c_char) -> *mut c_char;
+        pub fn waku_relay_unsubscribe(ctx: *mut c_void, pubsub_topic: *const c_char)
+            -> *mut c_char;
+        pub fn waku_decode_symmetric(
+            ctx: *mut c_void,
+            message: *const c_char,
+            symmetric_key: *const c_char,
+        ) -> *mut c_char;
+        pub fn waku_decode_asymmetric(
+            ctx: *mut c_void,
+            message: *const c_char,
+            private_key: *const c_char,
+        ) -> *mut c_char;
+    }
+}
+
+/// The key used to decrypt a received encrypted [`WakuMessage`], mirroring the two
+/// encryption schemes supported by `relay_publish_encrypt_asymmetric`/`_symmetric`
+pub enum DecryptionKey<'a> {
+    Asymmetric(&'a SecretKey),
+    Symmetric(&'a Key<Aes256Gcm>),
+}
+
+/// The default pubsub topic used across the Waku network, `/waku/2/default-waku/proto`
+pub fn waku_dafault_pubsub_topic() -> WakuPubSubTopic {
+    waku_create_pubsub_topic("default-waku".to_string(), Encoding::Proto)
+}
+
+/// Build a content topic out of its parts
+pub fn waku_create_content_topic(
+    application_name: String,
+    version: usize,
+    content_topic_name: String,
+    encoding: Encoding,
+) -> WakuContentTopic {
+    format!("/{application_name}/{version}/{content_topic_name}/{encoding}")
+        .parse()
+        .expect("content topic parts always produce a valid content topic")
+}
+
+/// Build a named pubsub topic out of its parts
+pub fn waku_create_pubsub_topic(topic_name: String, encoding: Encoding) -> WakuPubSubTopic {
+    format!("/waku/2/{topic_name}/{encoding}")
+        .parse()
+        .expect("pubsub topic parts always produce a valid pubsub topic")
+}
+
+/// Render `pubsub_topic` as a C string, falling back to the default pubsub topic when `None`.
+/// Shared with [`lightpush`](super::lightpush), which publishes on the same topics as relay
+pub(crate) fn pubsub_topic_c_string(pubsub_topic: Option<WakuPubSubTopic>) -> std::ffi::CString {
+    to_c_string(
+        pubsub_topic
+            .unwrap_or_else(waku_dafault_pubsub_topic)
+            .to_string(),
+    )
+}
+
+fn timeout_to_ms(timeout: Duration) -> i32 {
+    timeout.as_millis().try_into().unwrap_or(i32::MAX)
+}
+
+/// Publish a message using Waku Relay
+///
+/// wrapper around the `waku_relay_publish` FFI call
+pub(crate) fn waku_relay_publish_message(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    pubsub_topic: Option<WakuPubSubTopic>,
+    timeout: Duration,
+) -> Result<MessageId> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    unsafe {
+        decode_response(ffi::waku_relay_publish(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+            message.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Optionally sign, encrypt using asymmetric encryption and publish a message using Waku Relay
+///
+/// wrapper around the `waku_relay_publish_enc_asymmetric` FFI call
+pub(crate) fn waku_relay_publish_encrypt_asymmetric(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    pubsub_topic: Option<WakuPubSubTopic>,
+    public_key: &PublicKey,
+    signing_key: Option<&SecretKey>,
+    timeout: Duration,
+) -> Result<MessageId> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    let public_key = to_c_string(hex::encode(public_key.serialize()));
+    let signing_key = to_c_string(
+        signing_key
+            .map(|key| hex::encode(key.serialize()))
+            .unwrap_or_default(),
+    );
+    unsafe {
+        decode_response(ffi::waku_relay_publish_enc_asymmetric(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+            message.as_ptr(),
+            public_key.as_ptr(),
+            signing_key.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Optionally sign, encrypt using symmetric encryption and publish a message using Waku Relay
+///
+/// wrapper around the `waku_relay_publish_enc_symmetric` FFI call
+pub(crate) fn waku_relay_publish_encrypt_symmetric(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    pubsub_topic: Option<WakuPubSubTopic>,
+    symmetric_key: &Key<Aes256Gcm>,
+    signing_key: Option<&SecretKey>,
+    timeout: Duration,
+) -> Result<MessageId> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    let symmetric_key = to_c_string(hex::encode(symmetric_key));
+    let signing_key = to_c_string(
+        signing_key
+            .map(|key| hex::encode(key.serialize()))
+            .unwrap_or_default(),
+    );
+    unsafe {
+        decode_response(ffi::waku_relay_publish_enc_symmetric(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+            message.as_ptr(),
+            symmetric_key.as_ptr(),
+            signing_key.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
+
+/// Determine if there are enough peers to publish a message on a given pubsub topic
+///
+/// wrapper around the `waku_relay_enough_peers` FFI call
+pub(crate) fn waku_enough_peers(
+    ctx: &WakuNodeContext,
+    pubsub_topic: Option<WakuPubSubTopic>,
+) -> Result<bool> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    unsafe {
+        decode_response(ffi::waku_relay_enough_peers(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+        ))
+    }
+}
+
+/// Subscribe to a Waku Relay pubsub topic to receive messages
+///
+/// wrapper around the `waku_relay_subscribe` FFI call
+pub(crate) fn waku_relay_subscribe(
+    ctx: &WakuNodeContext,
+    pubsub_topic: Option<WakuPubSubTopic>,
+) -> Result<()> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    unsafe {
+        decode_response(ffi::waku_relay_subscribe(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+        ))
+    }
+}
+
+/// Closes the pubsub subscription to a pubsub topic. No more messages will be received from this pubsub topic
+///
+/// wrapper around the `waku_relay_unsubscribe` FFI call
+pub(crate) fn waku_relay_unsubscribe(
+    ctx: &WakuNodeContext,
+    pubsub_topic: Option<WakuPubSubTopic>,
+) -> Result<()> {
+    let pubsub_topic = pubsub_topic_c_string(pubsub_topic);
+    unsafe {
+        decode_response(ffi::waku_relay_unsubscribe(
+            ctx.as_ptr(),
+            pubsub_topic.as_ptr(),
+        ))
+    }
+}
+
+/// Decrypt a received encrypted message with `key`, closing the loop with
+/// `waku_relay_publish_encrypt_asymmetric`/`waku_relay_publish_encrypt_symmetric`
+///
+/// wrapper around the `waku_decode_asymmetric`/`waku_decode_symmetric` FFI calls
+pub(crate) fn waku_decode_payload(
+    ctx: &WakuNodeContext,
+    message: &WakuMessage,
+    key: DecryptionKey,
+) -> Result<DecodedPayload> {
+    let message =
+        to_c_string(serde_json::to_string(message).expect("message is always valid json"));
+    unsafe {
+        match key {
+            DecryptionKey::Asymmetric(private_key) => {
+                let private_key = to_c_string(hex::encode(private_key.serialize()));
+                decode_response(ffi::waku_decode_asymmetric(
+                    ctx.as_ptr(),
+                    message.as_ptr(),
+                    private_key.as_ptr(),
+                ))
+            }
+            DecryptionKey::Symmetric(symmetric_key) => {
+                let symmetric_key = to_c_string(hex::encode(symmetric_key));
+                decode_response(ffi::waku_decode_symmetric(
+                    ctx.as_ptr(),
+                    message.as_ptr(),
+                    symmetric_key.as_ptr(),
+                ))
+            }
+        }
+    }
+}
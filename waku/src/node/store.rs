@@ -0,0 +1,43 @@
+//! Waku Store/archive protocol
+//!
+//! wraps the [`store`](https://rfc.vac.dev/spec/36/#store) FFI calls
+
+use std::time::Duration;
+
+use super::context::WakuNodeContext;
+use super::utils::{decode_response, timeout_to_ms, to_c_string};
+use crate::general::{PeerId, Result, StoreQuery, StoreResponse};
+
+mod ffi {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    extern "C" {
+        pub fn waku_store_query(
+            ctx: *mut c_void,
+            query: *const c_char,
+            peer_id: *const c_char,
+            timeout_ms: c_int,
+        ) -> *mut c_char;
+    }
+}
+
+/// Query historical messages stored by a Store node
+///
+/// wrapper around the `waku_store_query` FFI call
+pub(crate) fn waku_store_query(
+    ctx: &WakuNodeContext,
+    query: &StoreQuery,
+    peer_id: PeerId,
+    timeout: Option<Duration>,
+) -> Result<StoreResponse> {
+    let query = to_c_string(serde_json::to_string(query).expect("query is always valid json"));
+    let peer_id = to_c_string(peer_id);
+    unsafe {
+        decode_response(ffi::waku_store_query(
+            ctx.as_ptr(),
+            query.as_ptr(),
+            peer_id.as_ptr(),
+            timeout_to_ms(timeout),
+        ))
+    }
+}
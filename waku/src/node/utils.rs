@@ -0,0 +1,42 @@
+//! Small helpers shared by the `node` submodules to talk to the `libwaku` FFI.
+
+use crate::general::{JsonResponse, Result};
+use serde::de::DeserializeOwned;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::time::Duration;
+
+mod ffi {
+    use std::os::raw::c_char;
+
+    extern "C" {
+        pub fn waku_utils_free(ptr: *mut c_char);
+    }
+}
+
+/// Convert a Rust string into a C string to be passed across the FFI boundary
+pub(crate) fn to_c_string(s: impl AsRef<str>) -> CString {
+    CString::new(s.as_ref()).expect("no null bytes in the input string")
+}
+
+/// Convert a timeout into the milliseconds `libwaku` FFI calls expect, with `None` meaning
+/// no timeout (`0`) and durations too long to fit saturating to `i32::MAX`
+pub(crate) fn timeout_to_ms(timeout: Option<Duration>) -> i32 {
+    timeout
+        .map(|timeout| timeout.as_millis().try_into().unwrap_or(i32::MAX))
+        .unwrap_or(0)
+}
+
+/// Take ownership of a `char*` returned by `libwaku`, decode it as UTF8, release the native
+/// allocation through `waku_utils_free` and parse the decoded string as the [`JsonResponse`]
+/// envelope every `libwaku` call wraps its result in
+pub(crate) unsafe fn decode_response<T: DeserializeOwned>(response: *mut c_char) -> Result<T> {
+    let decoded = CStr::from_ptr(response)
+        .to_str()
+        .expect("libwaku responses are always valid utf8")
+        .to_string();
+    ffi::waku_utils_free(response);
+    let decoded: JsonResponse<T> = serde_json::from_str(&decoded)
+        .unwrap_or_else(|err| panic!("could not decode libwaku response: {decoded} - {err}"));
+    decoded.into()
+}